@@ -4,6 +4,7 @@ use sdl2::event::{Event, WindowEvent};
 use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
 
+pub mod ai;
 pub mod map;
 
 pub fn launch(mut context: Context, game_map: GameMap) {
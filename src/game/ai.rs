@@ -0,0 +1,178 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::interface::maths::{cells_on_segment, dist};
+use crate::interface::Cell;
+
+use super::map::GameMap;
+
+/// A position, velocity and lap-progress triple: the unit of state the A* search plans over
+///
+/// `next_checkpoint` is how many of the track's ordered checkpoints have been crossed so far, so
+/// a finish crossing only counts once every checkpoint has been visited in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SearchState {
+    position: Cell,
+    velocity: (i32, i32),
+    next_checkpoint: usize,
+}
+
+/// A node on the A* frontier, ordered by ascending f-cost (turns taken so far plus heuristic)
+struct Node {
+    state: SearchState,
+    g_cost: u32,
+    f_cost: f32,
+    path: Vec<(i32, i32)>,
+    /// Whether the move that produced this node crossed the finish line
+    reached_finish: bool,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.f_cost == other.f_cost
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so a `BinaryHeap` (a max-heap) pops the lowest f-cost first
+        other
+            .f_cost
+            .partial_cmp(&self.f_cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Admissible heuristic: distance to the goal divided by the fastest speed that could plausibly
+/// carry the car there: its current speed plus what it could still gain by accelerating over the
+/// distance left
+fn heuristic(position: Cell, velocity: (i32, i32), goal: Cell) -> f32 {
+    let d = dist(&position.center_point(), &goal.center_point());
+    let speed = ((velocity.0 * velocity.0 + velocity.1 * velocity.1) as f32).sqrt();
+    let max_speed = (2. * d).sqrt().max(1.) + speed;
+    d / max_speed
+}
+
+/// Plan an optimal lap from `start` around every `checkpoints` cell in order and across the
+/// finish line, returning the per-turn velocity sequence the car should follow, or `None` if no
+/// such route exists
+///
+/// `checkpoints` is the track's ordered checkpoint cells (see `GameMap::checkpoints`), and
+/// `finish_cells` is every cell the finish line passes through (see `GameMap::finish_cells`). A
+/// finish crossing only counts once every checkpoint has been crossed, in order. `speed_cap`
+/// bounds each velocity component, keeping the (position, velocity) search space finite
+pub fn plan_lap(
+    map: &GameMap,
+    start: Cell,
+    checkpoints: &[Cell],
+    finish_cells: &[Cell],
+    speed_cap: i32,
+) -> Option<Vec<(i32, i32)>> {
+    let start_state = SearchState {
+        position: start,
+        velocity: (0, 0),
+        next_checkpoint: 0,
+    };
+
+    // The cell(s) the heuristic should aim for: the next unvisited checkpoint, or the finish
+    // line once every checkpoint has been crossed
+    let goals = |next_checkpoint: usize| -> &[Cell] {
+        if next_checkpoint < checkpoints.len() {
+            &checkpoints[next_checkpoint..=next_checkpoint]
+        } else {
+            finish_cells
+        }
+    };
+
+    let h_cost = |position: Cell, velocity: (i32, i32), next_checkpoint: usize| {
+        goals(next_checkpoint)
+            .iter()
+            .map(|&goal| heuristic(position, velocity, goal))
+            .fold(f32::MAX, f32::min)
+    };
+
+    let mut frontier = BinaryHeap::new();
+    frontier.push(Node {
+        state: start_state,
+        g_cost: 0,
+        f_cost: h_cost(start, (0, 0), 0),
+        path: Vec::new(),
+        reached_finish: checkpoints.is_empty() && finish_cells.contains(&start),
+    });
+
+    let mut closed = HashSet::new();
+
+    while let Some(node) = frontier.pop() {
+        if node.reached_finish {
+            return Some(node.path);
+        }
+
+        if !closed.insert(node.state) {
+            continue;
+        }
+
+        for ax in -1..=1 {
+            for ay in -1..=1 {
+                let velocity = (node.state.velocity.0 + ax, node.state.velocity.1 + ay);
+
+                if velocity.0.abs() > speed_cap || velocity.1.abs() > speed_cap {
+                    continue;
+                }
+
+                let position = Cell(
+                    node.state.position.0 + velocity.0,
+                    node.state.position.1 + velocity.1,
+                );
+
+                // Sample every cell the move passes through, not just where it lands: a fast
+                // move can cross a wall, or the finish line, between cells
+                let segment = cells_on_segment(node.state.position, position);
+                if segment.iter().any(|&cell| !map.get_tile(cell).is_drivable()) {
+                    continue;
+                }
+
+                // Advance past every checkpoint this move's segment crosses, in order: a fast
+                // move can cross more than one checkpoint in a single turn
+                let mut next_checkpoint = node.state.next_checkpoint;
+                while next_checkpoint < checkpoints.len()
+                    && segment.contains(&checkpoints[next_checkpoint])
+                {
+                    next_checkpoint += 1;
+                }
+
+                let next_state = SearchState {
+                    position,
+                    velocity,
+                    next_checkpoint,
+                };
+                if closed.contains(&next_state) {
+                    continue;
+                }
+
+                let g_cost = node.g_cost + 1;
+
+                let mut path = node.path.clone();
+                path.push(velocity);
+
+                frontier.push(Node {
+                    state: next_state,
+                    g_cost,
+                    f_cost: g_cost as f32 + h_cost(position, velocity, next_checkpoint),
+                    path,
+                    reached_finish: next_checkpoint >= checkpoints.len()
+                        && segment.iter().any(|cell| finish_cells.contains(cell)),
+                });
+            }
+        }
+    }
+
+    None
+}
@@ -1,11 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
+use serde::Deserialize;
 use sdl2::pixels::Color;
 
-use crate::interface::Cell;
+use crate::interface::maths::{cells_on_segment, flatten_bezier, trace_contours, Directions, Side};
+use crate::interface::{Cell, WorldPosition};
+
+pub mod generator;
 
 /// Represent a tile on the map and all its properties
 ///
@@ -51,6 +55,102 @@ impl Tile {
             Self::Ice => Color::BLUE,
         }
     }
+
+    /// Whether a car can drive over this tile: off-track `Empty` space and `Wall` block movement
+    pub fn is_drivable(&self) -> bool {
+        !matches!(self, Self::Empty | Self::Wall)
+    }
+
+    /// Pick the sprite variant to draw this tile as, from its 8 surrounding tiles given in
+    /// `Directions::iter()` order
+    pub fn autotile_variant(self, neighbors: [Tile; 8]) -> TileVariant {
+        let mut mask = 0u8;
+        for (i, neighbor) in neighbors.into_iter().enumerate() {
+            if neighbor == self {
+                mask |= 1 << i;
+            }
+        }
+
+        let bit = |d: Directions| mask & (1 << d as u8) != 0;
+        let (up, right, down, left) = (
+            bit(Directions::Up),
+            bit(Directions::Right),
+            bit(Directions::Down),
+            bit(Directions::Left),
+        );
+
+        match (up, right, down, left) {
+            (false, false, false, false) => TileVariant::Isolated,
+            (true, true, true, true) => {
+                if Directions::iter().all(bit) {
+                    TileVariant::Full
+                } else if !bit(Directions::UpRight) {
+                    TileVariant::InnerCorner(Side::Up, Side::Right)
+                } else if !bit(Directions::DownRight) {
+                    TileVariant::InnerCorner(Side::Down, Side::Right)
+                } else if !bit(Directions::DownLeft) {
+                    TileVariant::InnerCorner(Side::Down, Side::Left)
+                } else {
+                    TileVariant::InnerCorner(Side::Up, Side::Left)
+                }
+            }
+            (true, true, true, false) => TileVariant::Edge(Side::Left),
+            (true, true, false, true) => TileVariant::Edge(Side::Down),
+            (false, true, true, true) => TileVariant::Edge(Side::Up),
+            (true, false, true, true) => TileVariant::Edge(Side::Right),
+            (true, true, false, false) => TileVariant::OuterCorner(Side::Down, Side::Left),
+            (false, true, true, false) => TileVariant::OuterCorner(Side::Up, Side::Left),
+            (false, false, true, true) => TileVariant::OuterCorner(Side::Up, Side::Right),
+            (true, false, false, true) => TileVariant::OuterCorner(Side::Down, Side::Right),
+            _ => TileVariant::Other(mask),
+        }
+    }
+}
+
+/// The sprite piece a tile should be drawn as, chosen from which of its neighbors share its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileVariant {
+    /// None of the four side neighbors shares this tile's type; diagonal neighbors are not checked
+    Isolated,
+    /// Every neighbor, including diagonals, shares this tile's type
+    Full,
+    /// A single straight side is missing its matching neighbor
+    Edge(Side),
+    /// Two adjacent sides are both missing their matching neighbor
+    OuterCorner(Side, Side),
+    /// Both cardinal sides bordering a corner match but the diagonal between them doesn't
+    InnerCorner(Side, Side),
+    /// A mask that doesn't reduce to one of the simple cases above
+    Other(u8),
+}
+
+/// Spawn information for a single player: starting cell and car color
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayerData {
+    pub position: [i32; 2],
+    pub color: [u32; 3],
+}
+
+/// A single cubic bezier segment of a curved track boundary, given as four control points
+#[derive(Debug, Clone, Deserialize)]
+struct BezierCurve {
+    p0: [f32; 2],
+    p1: [f32; 2],
+    p2: [f32; 2],
+    p3: [f32; 2],
+}
+
+/// Raw layout of a track file, deserialized from json5 before being turned into a `GameMap`
+#[derive(Debug, Deserialize)]
+struct TrackFile {
+    grid: Vec<String>,
+    players: Vec<PlayerData>,
+    #[serde(default)]
+    checkpoints: Vec<[i32; 2]>,
+    finish: [[i32; 2]; 2],
+    /// Curved boundaries, each a closed loop of consecutive bezier segments
+    #[serde(default)]
+    boundaries: Vec<Vec<BezierCurve>>,
 }
 
 /// Represent the map and the objects/players on it
@@ -58,6 +158,10 @@ impl Tile {
 pub struct GameMap {
     terrain: HashMap<Cell, Tile>,
     default_tile: Tile,
+    players: Vec<(Cell, Color)>,
+    checkpoints: Vec<Cell>,
+    finish: Option<(Cell, Cell)>,
+    boundaries: Vec<Vec<(WorldPosition, WorldPosition, WorldPosition, WorldPosition)>>,
 }
 
 impl GameMap {
@@ -66,6 +170,10 @@ impl GameMap {
         GameMap {
             terrain: HashMap::new(),
             default_tile: Tile::Empty,
+            players: Vec::new(),
+            checkpoints: Vec::new(),
+            finish: None,
+            boundaries: Vec::new(),
         }
     }
 
@@ -86,9 +194,17 @@ impl GameMap {
         }
     }
 
-    /// Generate a new map from a file
+    /// Synthesize a random closed-loop track as a guided random walk on the cell grid
+    ///
+    /// The same `seed` always reproduces the same track. Fails rather than handing back an
+    /// untraversable map if no closed, wide-enough loop could be carved out in time
+    pub fn generate_random(seed: u64, width: u32, height: u32) -> Result<Self, String> {
+        generator::generate(seed, width, height)
+    }
+
+    /// Generate a new map from the old loose pipe-delimited text grid
     ///
-    /// Prototype, miss a lot of features for now
+    /// Kept as a fallback for maps that haven't been ported to `from_track_file` yet
     pub fn generate_from_file(path: &Path) -> Result<Self, String> {
         let mut file = File::open(path).map_err(|x| x.to_string())?;
 
@@ -109,8 +225,154 @@ impl GameMap {
         Ok(map)
     }
 
+    /// Generate a new map from a structured json5 track file: the tile grid plus player spawns,
+    /// ordered checkpoints and a finish line
+    pub fn from_track_file(path: &Path) -> Result<Self, String> {
+        let mut file = File::open(path).map_err(|x| x.to_string())?;
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|x| x.to_string())?;
+
+        let track: TrackFile = json5::from_str(&content).map_err(|x| x.to_string())?;
+
+        let mut map = GameMap::empty();
+        for (y, row) in track.grid.iter().enumerate() {
+            for (x, tile) in row.trim().split('|').enumerate() {
+                map.set_tile(
+                    (x as i32, y as i32),
+                    Tile::read_tile(tile).map_err(|err| format!("{} at ({};{})", err, x, y))?,
+                );
+            }
+        }
+
+        map.players = track
+            .players
+            .iter()
+            .map(|player| {
+                (
+                    Cell::from((player.position[0], player.position[1])),
+                    Color::RGB(
+                        player.color[0] as u8,
+                        player.color[1] as u8,
+                        player.color[2] as u8,
+                    ),
+                )
+            })
+            .collect();
+
+        map.checkpoints = track
+            .checkpoints
+            .iter()
+            .map(|c| Cell::from((c[0], c[1])))
+            .collect();
+
+        map.finish = Some((
+            Cell::from((track.finish[0][0], track.finish[0][1])),
+            Cell::from((track.finish[1][0], track.finish[1][1])),
+        ));
+
+        map.boundaries = track
+            .boundaries
+            .iter()
+            .map(|curves| {
+                curves
+                    .iter()
+                    .map(|c| {
+                        (
+                            WorldPosition::from((c.p0[0], c.p0[1])),
+                            WorldPosition::from((c.p1[0], c.p1[1])),
+                            WorldPosition::from((c.p2[0], c.p2[1])),
+                            WorldPosition::from((c.p3[0], c.p3[1])),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(map)
+    }
+
     /// Iterate over every tile in the map
     pub fn iter_tiles(&self) -> impl Iterator<Item = ((i32, i32), &Tile)> {
         self.terrain.iter().map(|x| ((x.0 .0, x.0 .1), x.1))
     }
+
+    /// Iterate over every player's spawn cell and car color
+    pub fn iter_players(&self) -> impl Iterator<Item = &(Cell, Color)> {
+        self.players.iter()
+    }
+
+    /// Checkpoints that must be visited, in order, before the finish line counts
+    pub fn checkpoints(&self) -> &[Cell] {
+        &self.checkpoints
+    }
+
+    /// The two cells forming the finish line, if the map was loaded from a track file
+    pub fn finish_segment(&self) -> Option<(Cell, Cell)> {
+        self.finish
+    }
+
+    /// Every cell the finish line passes through, rasterizing the two endpoints from
+    /// `finish_segment` into the intermediate cells a lap needs to cross
+    pub fn finish_cells(&self) -> Vec<Cell> {
+        match self.finish {
+            Some((start, end)) => cells_on_segment(start, end),
+            None => Vec::new(),
+        }
+    }
+
+    /// Trace the outlines of every contiguous `Tile::Wall` region into closed polygons suitable
+    /// for `sdf_multiple_polygons`: outer boundaries clockwise, holes counter-clockwise
+    pub fn wall_contours(&self) -> Vec<Vec<WorldPosition>> {
+        let mut border_cells = HashSet::new();
+
+        for (pos, _) in self.iter_tiles().filter(|(_, tile)| **tile == Tile::Wall) {
+            let cell = Cell::from(pos);
+            for side in Side::iter() {
+                let neighbor = Cell::from((cell.0 + side.dir().0, cell.1 + side.dir().1));
+                if self.get_tile(neighbor) != Tile::Wall {
+                    border_cells.insert((cell, neighbor));
+                }
+            }
+        }
+
+        // Walls can also come from the default tile rather than stored cells, so seed the same
+        // borders from the non-wall side as well
+        for (pos, _) in self.iter_tiles().filter(|(_, tile)| **tile != Tile::Wall) {
+            let cell = Cell::from(pos);
+            for side in Side::iter() {
+                let neighbor = Cell::from((cell.0 + side.dir().0, cell.1 + side.dir().1));
+                if self.get_tile(neighbor) == Tile::Wall {
+                    border_cells.insert((neighbor, cell));
+                }
+            }
+        }
+
+        trace_contours(border_cells, |cell| self.get_tile(cell) == Tile::Wall)
+            .into_iter()
+            .map(|(_, contour)| contour)
+            .collect()
+    }
+
+    /// Flatten the track file's curved boundaries into polygons ready for `sdf_polygon`/
+    /// `sdf_multiple_polygons`, at a caller-chosen `tolerance` so zoom level can refine curve
+    /// smoothness
+    pub fn boundary_polygons(&self, tolerance: f32) -> Vec<Vec<WorldPosition>> {
+        self.boundaries
+            .iter()
+            .map(|curves| {
+                let mut polygon = match curves.first() {
+                    Some(&(p0, ..)) => vec![p0],
+                    None => Vec::new(),
+                };
+
+                for &(p0, p1, p2, p3) in curves {
+                    polygon.extend(flatten_bezier(p0, p1, p2, p3, tolerance).into_iter().skip(1));
+                }
+
+                polygon
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use crate::interface::maths::{Directions, Side};
+use crate::interface::Cell;
+
+use super::{GameMap, Tile};
+
+/// How many cells around the walk path are carved into road on each side, widening the track
+const ROAD_RADIUS: i32 = 2;
+
+/// How many straight steps the walk must take before it is allowed to turn again
+const TURN_COOLDOWN: u32 = 3;
+
+/// How many straight steps in a row before a straightaway is considered long enough to sprinkle
+const STRAIGHTAWAY_LENGTH: u32 = 5;
+
+/// A small deterministic PRNG (xorshift64) so a given seed always reproduces the same track
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Next pseudo-random value in `0..bound`
+    fn below(&mut self, bound: u32) -> u32 {
+        (self.next_u64() % bound as u64) as u32
+    }
+}
+
+/// How many seeded attempts to make before giving up on a traversable loop
+const ATTEMPTS: u64 = 256;
+
+/// Whether every cell in `road_cells` can be reached from `start` by stepping between adjacent
+/// (including diagonal) road cells, i.e. the carved track is a single connected corridor rather
+/// than pinched apart by a near-self-intersecting walk
+fn is_connected(road_cells: &HashSet<Cell>, start: Cell) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![start];
+
+    while let Some(cell) = stack.pop() {
+        if !visited.insert(cell) {
+            continue;
+        }
+        for dir in Directions::iter_dir() {
+            let neighbor = Cell(cell.0 + dir.0, cell.1 + dir.1);
+            if road_cells.contains(&neighbor) && !visited.contains(&neighbor) {
+                stack.push(neighbor);
+            }
+        }
+    }
+
+    visited.len() == road_cells.len()
+}
+
+/// Synthesize a random closed-loop track as a guided random walk on the cell grid
+///
+/// The same `seed` always reproduces the same track. Returns an error instead of a blank,
+/// untraversable map if no closed, connected loop could be carved out of `width`x`height` in
+/// `ATTEMPTS` tries
+pub(super) fn generate(seed: u64, width: u32, height: u32) -> Result<GameMap, String> {
+    (0..ATTEMPTS)
+        .find_map(|attempt| try_generate(seed.wrapping_add(attempt), width, height))
+        .ok_or_else(|| {
+            format!(
+                "failed to generate a traversable {}x{} track from seed {} after {} attempts",
+                width, height, seed, ATTEMPTS
+            )
+        })
+}
+
+fn try_generate(seed: u64, width: u32, height: u32) -> Option<GameMap> {
+    let mut rng = Rng::new(seed);
+
+    let start = Cell((width / 2) as i32, (height / 2) as i32);
+    let mut side = Side::iter().nth(rng.below(4) as usize).unwrap();
+    let mut turn_budget = (width + height) / 2;
+    let mut cooldown = 0;
+
+    let mut path = vec![start];
+    let mut straight_run = vec![0u32];
+
+    loop {
+        let turned = cooldown == 0 && turn_budget > 0 && rng.below(3) == 0;
+        if turned {
+            side = if rng.below(2) == 0 {
+                side.turn_left()
+            } else {
+                side.turn_right()
+            };
+            turn_budget -= 1;
+            cooldown = TURN_COOLDOWN;
+        } else if cooldown > 0 {
+            cooldown -= 1;
+        }
+
+        let dir = side.dir();
+        let current = *path.last().unwrap();
+        let next = Cell(current.0 + dir.0, current.1 + dir.1);
+
+        if next.0 < 0 || next.1 < 0 || next.0 >= width as i32 || next.1 >= height as i32 {
+            return None;
+        }
+
+        // Long enough and back near the origin: close the loop
+        if path.len() > (width + height) as usize
+            && (next.0 - start.0).abs() <= 1
+            && (next.1 - start.1).abs() <= 1
+        {
+            break;
+        }
+
+        // Reject self-intersections that would pinch the road narrower than the car can pass
+        if path.contains(&next) {
+            return None;
+        }
+
+        straight_run.push(if turned {
+            0
+        } else {
+            straight_run.last().unwrap() + 1
+        });
+        path.push(next);
+
+        if path.len() > (4 * (width + height)) as usize {
+            return None;
+        }
+    }
+
+    let mut map = GameMap::empty();
+    map.default_tile = Tile::Wall;
+
+    // Carve the filled square neighborhood around each path cell, not just the rays radiating
+    // from it, or diagonal offsets are left as wall slivers poking into the corridor
+    let mut road_cells = HashSet::new();
+    for &cell in &path {
+        for dx in -ROAD_RADIUS..=ROAD_RADIUS {
+            for dy in -ROAD_RADIUS..=ROAD_RADIUS {
+                road_cells.insert(Cell(cell.0 + dx, cell.1 + dy));
+            }
+        }
+    }
+
+    // Reject a carved road that is not one region reachable from the start; a walk that passes
+    // close to itself merges the two stretches of corridor and is kept
+    if !is_connected(&road_cells, start) {
+        return None;
+    }
+
+    for &cell in &road_cells {
+        map.set_tile(cell, Tile::Road);
+    }
+
+    for (i, &cell) in path.iter().enumerate() {
+        if straight_run[i] >= STRAIGHTAWAY_LENGTH && rng.below(4) == 0 {
+            let patch = if rng.below(2) == 0 {
+                Tile::Ice
+            } else {
+                Tile::Gravel
+            };
+            map.set_tile(cell, patch);
+        }
+    }
+
+    Some(map)
+}
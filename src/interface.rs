@@ -6,8 +6,8 @@ use sdl2::render::{Canvas, Texture, TextureCreator};
 use sdl2::video::{Window, WindowContext};
 use sdl2::EventPump;
 
-use crate::game::map::{GameMap, Tile};
-use maths::{sdf_multiple_polygons, Side};
+use crate::game::map::{GameMap, Tile, TileVariant};
+use maths::{sdf_multiple_polygons, trace_contours, Directions, Side};
 
 pub use maths::{Cell, WindowPosition, WorldPosition};
 
@@ -155,6 +155,9 @@ impl<'a> View<'a> {
 
         let mut border_cells = HashSet::new();
 
+        // Autotile variant of each cell, used to give edges and corners distinct shading below
+        let mut variants: HashMap<Cell, TileVariant> = HashMap::new();
+
         for ((x, y), _tile_type) in self.game_map.iter_tiles() {
             if let Tile::Empty = self.game_map.get_tile((x, y)) {
                 continue;
@@ -177,79 +180,26 @@ impl<'a> View<'a> {
             for (_, p) in surrounding.into_iter().filter(|x| x.0 != current_tile) {
                 border_cells.insert((Cell::from((x, y)), Cell::from(p)));
             }
+
+            let mut neighbors = [Tile::Empty; 8];
+            for (i, p) in Directions::iter_dir().enumerate() {
+                neighbors[i] = self.game_map.get_tile((x + p.0, y + p.1));
+            }
+            variants.insert(Cell::from((x, y)), current_tile.autotile_variant(neighbors));
         }
 
-        // Vector containing all the borders
+        // Vector containing all the borders, grouped by the tile type they bound
         let mut borders_list: HashMap<Tile, Vec<Vec<WorldPosition>>> = HashMap::new();
 
-        // Just help for readibility
-        fn move_cell(cell: Cell, dir: Side) -> Cell {
-            Cell::from((cell.0 + dir.dir().0, cell.1 + dir.dir().1))
+        for (tile, border) in trace_contours(border_cells, |cell| self.game_map.get_tile(cell)) {
+            borders_list.entry(tile).or_default().push(border);
         }
 
-        while !border_cells.is_empty() {
-            let mut border = Vec::new();
-
-            let first = *border_cells.iter().next().unwrap();
-            let tile = self.game_map.get_tile(first.0);
-
-            let mut current = first;
-            border.push(current);
-
-            // the current direction to follow the border with the right hand (from the inside)
-            let mut current_dir =
-                Side::from_dir((current.1 .0 - current.0 .0, current.1 .1 - current.0 .1))
-                    .unwrap()
-                    .turn_left();
-
-            loop {
-                // Get rid of that border so that it isn't inspected again
-                border_cells.remove(&current);
-
-                // If the cell to the right is in, the area, we must turn and move
-                if self
-                    .game_map
-                    .get_tile(move_cell(current.0, current_dir.turn_right()))
-                    == tile
-                {
-                    current_dir = current_dir.turn_right();
-                    current.0 = move_cell(current.0, current_dir);
-
-                    // Otherwise, if the cell in front of the current onr is in the area, move on
-                } else if self.game_map.get_tile(move_cell(current.0, current_dir)) == tile {
-                    current.0 = move_cell(current.0, current_dir);
-
-                // Finally, if it isn't in al well, turn left
-                } else {
-                    current_dir = current_dir.turn_left();
-                }
-
-                // recalculate the border faced cell
-                current.1 = move_cell(current.0, current_dir.turn_right());
-
-                // Add the tile to the border list in the order if it is a border
-                if self.game_map.get_tile(current.1) != tile {
-                    border.push(current);
-                }
-
-                if current == first {
-                    break;
-                }
-            }
-
-            let border = border
-                .into_iter()
-                .map(|x| {
-                    let (inside, outside) = (x.0.center_point(), x.1.center_point());
-                    WorldPosition::from(((inside.0 + outside.0) / 2., (inside.1 + outside.1) / 2.))
-                })
-                .collect();
-
-            if borders_list.contains_key(&tile) {
-                borders_list.get_mut(&tile).unwrap().push(border);
-            } else {
-                borders_list.insert(tile, vec![border]);
-            }
+        // Curved track boundaries feed the same wall collision/render pipeline as the tile
+        // contours above, flattened to one pixel of world-space tolerance at this render scale
+        let boundary_tolerance = 1. / PRE_RENDERING_CELL_SIZE as f32;
+        for polygon in self.game_map.boundary_polygons(boundary_tolerance) {
+            borders_list.entry(Tile::Wall).or_default().push(polygon);
         }
 
         let starting_world_pos = Cell::from(cell_range.0).start_point();
@@ -270,9 +220,14 @@ impl<'a> View<'a> {
 
                             if dist <= 0. {
                                 let a = (((-dist * 10.).sin() * 64.).round()) as u8;
+                                let edge_highlight = match variants.get(&world_pos.cell()) {
+                                    Some(TileVariant::OuterCorner(..) | TileVariant::InnerCorner(..)) => 24,
+                                    Some(TileVariant::Edge(_)) => 12,
+                                    _ => 0,
+                                };
                                 let color = tile.tile_color();
                                 buffer[offset] = color.b;
-                                buffer[offset + 1] = color.g + a;
+                                buffer[offset + 1] = color.g.saturating_add(a).saturating_add(edge_highlight);
                                 buffer[offset + 2] = color.r;
                                 buffer[offset + 3] = 255;
 
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 /// Represent a position on the window in pixel, therefore is most of the time non-negative
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct WindowPosition(pub i32, pub i32);
@@ -53,7 +55,7 @@ impl Cell {
 }
 
 /// Represent the different sides of a square
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Side {
     Up,
     Right,
@@ -199,6 +201,36 @@ pub fn dist(a: &WorldPosition, b: &WorldPosition) -> f32 {
     ((a.0 - b.0) * (a.0 - b.0) + (a.1 - b.1) * (a.1 - b.1)).sqrt()
 }
 
+/// Cells crossed by the straight move from `from` to `to`, sampled with Bresenham's line algorithm
+pub fn cells_on_segment(from: Cell, to: Cell) -> Vec<Cell> {
+    let (mut x, mut y) = (from.0, from.1);
+    let (x1, y1) = (to.0, to.1);
+
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    let mut cells = Vec::new();
+    loop {
+        cells.push(Cell(x, y));
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+    cells
+}
+
 /// Vector formed by two points
 pub fn vec(a: &WorldPosition, b: &WorldPosition) -> (f32, f32) {
     (b.0 - a.0, b.1 - a.1)
@@ -288,3 +320,160 @@ pub fn sdf_multiple_polygons(
     }
     dist
 }
+
+/// Midpoint between two points
+fn midpoint(a: WorldPosition, b: WorldPosition) -> WorldPosition {
+    ((a.0 + b.0) / 2., (a.1 + b.1) / 2.).into()
+}
+
+/// Perpendicular distance from `point` to the infinite line through `a` and `b`
+///
+/// Falls back to the plain distance to `a` when `a` and `b` coincide, since a closed bezier
+/// curve (start and end control point equal) has no line through them to measure against
+fn line_distance(point: &WorldPosition, a: &WorldPosition, b: &WorldPosition) -> f32 {
+    if (a.0 - b.0).abs() < 10e-6 && (a.1 - b.1).abs() < 10e-6 {
+        return dist(point, a);
+    }
+
+    let (la, lb, lc) = (
+        b.1 - a.1,
+        a.0 - b.0,
+        a.1 * (b.0 - a.0) - a.0 * (b.1 - a.1),
+    );
+
+    (la * point.0 + lb * point.1 + lc).abs() / (la * la + lb * lb).sqrt()
+}
+
+/// How deep `flatten_bezier` may recurse before giving up on reaching `tolerance`, as a backstop
+/// against floating-point jitter preventing convergence
+const MAX_FLATTEN_DEPTH: u32 = 24;
+
+/// Subdivide a cubic bezier curve (control points `p0`..`p3`) into a polyline whose deviation
+/// from the true curve stays under `tolerance`
+///
+/// Uses recursive De Casteljau midpoint subdivision: the curve is split in half until its control
+/// points are within `tolerance` of the chord from `p0` to `p3`, at which point the chord's
+/// endpoints are emitted. The returned polyline always starts at `p0`; a closed curve (`p0` equal
+/// to `p3`) is still subdivided rather than collapsed, so the loop isn't discarded.
+pub fn flatten_bezier(
+    p0: WorldPosition,
+    p1: WorldPosition,
+    p2: WorldPosition,
+    p3: WorldPosition,
+    tolerance: f32,
+) -> Vec<WorldPosition> {
+    let mut points = vec![p0];
+    flatten_bezier_into(p0, p1, p2, p3, tolerance, MAX_FLATTEN_DEPTH, &mut points);
+    points
+}
+
+fn flatten_bezier_into(
+    p0: WorldPosition,
+    p1: WorldPosition,
+    p2: WorldPosition,
+    p3: WorldPosition,
+    tolerance: f32,
+    depth: u32,
+    points: &mut Vec<WorldPosition>,
+) {
+    let is_flat = depth == 0
+        || (line_distance(&p1, &p0, &p3) <= tolerance && line_distance(&p2, &p0, &p3) <= tolerance);
+
+    if is_flat {
+        points.push(p3);
+        return;
+    }
+
+    let p01 = midpoint(p0, p1);
+    let p12 = midpoint(p1, p2);
+    let p23 = midpoint(p2, p3);
+    let p012 = midpoint(p01, p12);
+    let p123 = midpoint(p12, p23);
+    let mid = midpoint(p012, p123);
+
+    flatten_bezier_into(p0, p01, p012, mid, tolerance, depth - 1, points);
+    flatten_bezier_into(mid, p123, p23, p3, tolerance, depth - 1, points);
+}
+
+/// The cell corner a border-tracing walk is passing when it is following `dir` along the
+/// right-hand side of `cell` (the corner "ahead" of the walk on that side)
+fn leading_corner(cell: Cell, dir: Side) -> WorldPosition {
+    let (x, y) = (cell.0 as f32, cell.1 as f32);
+    match dir {
+        Side::Up => (x + 1., y).into(),
+        Side::Right => (x + 1., y + 1.).into(),
+        Side::Down => (x, y + 1.).into(),
+        Side::Left => (x, y).into(),
+    }
+}
+
+/// Trace connected cell borders into closed polygons using square/Moore contour tracing: follow
+/// each boundary with its region on the left hand and the outside cell on the right, emitting the actual cell corner `WorldPosition`
+/// at every step (not an edge midpoint, which would cut every corner into the region)
+///
+/// `border_cells` seeds every (inside, outside) cell pair across a border, typically every pair
+/// of adjacent cells whose `region_of` differs. `region_of` identifies which region a cell
+/// belongs to; cells sharing a key are walked as part of the same loop. Consumes `border_cells`.
+///
+/// Returns one polygon per traced loop tagged with its region's key (outer boundaries clockwise,
+/// holes counter-clockwise, matching the convention `sdf_multiple_polygons` expects)
+pub fn trace_contours<K: PartialEq>(
+    mut border_cells: HashSet<(Cell, Cell)>,
+    region_of: impl Fn(Cell) -> K,
+) -> Vec<(K, Vec<WorldPosition>)> {
+    fn move_cell(cell: Cell, dir: Side) -> Cell {
+        Cell::from((cell.0 + dir.dir().0, cell.1 + dir.dir().1))
+    }
+
+    let mut contours = Vec::new();
+
+    while !border_cells.is_empty() {
+        let first = *border_cells.iter().next().unwrap();
+        let region = region_of(first.0);
+
+        let mut current = first;
+
+        // the current direction to follow the border with the right hand (from the inside)
+        let mut current_dir =
+            Side::from_dir((current.1 .0 - current.0 .0, current.1 .1 - current.0 .1))
+                .unwrap()
+                .turn_left();
+
+        let mut contour = vec![leading_corner(current.0, current_dir)];
+
+        loop {
+            // Get rid of that border so that it isn't inspected again
+            border_cells.remove(&current);
+
+            // If the cell to the right is in the region, we must turn and move
+            if region_of(move_cell(current.0, current_dir.turn_right())) == region {
+                current_dir = current_dir.turn_right();
+                current.0 = move_cell(current.0, current_dir);
+
+            // Otherwise, if the cell in front of the current one is in the region, move on
+            } else if region_of(move_cell(current.0, current_dir)) == region {
+                current.0 = move_cell(current.0, current_dir);
+
+            // Finally, if it isn't in either, turn left
+            } else {
+                current_dir = current_dir.turn_left();
+            }
+
+            // recalculate the border faced cell
+            current.1 = move_cell(current.0, current_dir.turn_right());
+
+            // Add the corner to the contour if it is still a border
+            if region_of(current.1) != region {
+                contour.push(leading_corner(current.0, current_dir));
+            }
+
+            if current == first {
+                break;
+            }
+        }
+
+        contours.push((region, contour));
+    }
+
+    contours
+}